@@ -0,0 +1,58 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use futures::executor;
+use net_tiger::executor::Executor;
+
+/// A future that wakes itself and yields a set number of times before completing, so that every
+/// poll after the first one is the result of a wake.
+struct Yield(usize);
+
+impl Future for Yield {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
+        match self.0 {
+            0 => Poll::Ready(()),
+            _ => {
+                self.0 -= 1;
+                ctx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// How long it takes to spawn a batch of tasks, and run them to completion.
+fn spawn(c: &mut Criterion) {
+    c.bench_function("spawn 32 tasks", |b| {
+        let mut executor = Executor::new();
+        b.iter(|| {
+            for _ in 0..32usize {
+                executor.spawn(async {});
+            }
+            // the main future has to yield, or the spawned tasks never get polled
+            executor.complete(Yield(1)).unwrap();
+        })
+    });
+}
+
+/// How long it takes to get from a task waking itself to that task being polled again, compared
+/// against the executor that comes with the futures crate.
+fn wake_to_poll(c: &mut Criterion) {
+    c.bench_function("wake to poll 1000 times", |b| {
+        let mut executor = Executor::new();
+        b.iter(|| executor.complete(Yield(1000)).unwrap())
+    });
+
+    c.bench_function("wake to poll 1000 times (futures baseline)", |b| {
+        b.iter(|| executor::block_on(Yield(1000)))
+    });
+}
+
+criterion_group!(benches, spawn, wake_to_poll);
+criterion_main!(benches);
@@ -0,0 +1,114 @@
+use std::{
+    future::Future,
+    os::raw::c_int,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use futures::io::AsyncRead;
+use net_tiger::{
+    executor::Executor,
+    reactor::{Reactor, Stdin},
+};
+
+/// Replace stdin with the read end of a new pipe, so that the benchmark can control when stdin is
+/// readable. Returns the write end of the pipe.
+fn pipe_stdin() -> c_int {
+    let mut fds = [0; 2];
+    unsafe {
+        assert_eq!(0, libc::pipe(fds.as_mut_ptr()));
+        assert_ne!(-1, libc::dup2(fds[0], libc::STDIN_FILENO));
+        libc::close(fds[0]);
+    }
+
+    fds[1]
+}
+
+/// A future that starts waiting for stdin to be readable, and only then writes a byte into the
+/// pipe behind it. Nothing else wakes it, so it can only complete once the reactor has spun and
+/// picked up the readiness event.
+struct RoundTrip<'a> {
+    input: &'a mut Stdin,
+    pipe: c_int,
+    written: bool,
+}
+
+impl<'a> RoundTrip<'a> {
+    fn new(input: &'a mut Stdin, pipe: c_int) -> Self {
+        RoundTrip {
+            input,
+            pipe,
+            written: false,
+        }
+    }
+}
+
+impl Future for RoundTrip<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
+        let mut buffer = [0; 1];
+        if let Poll::Ready(read) = Pin::new(&mut *self.input).poll_read(ctx, &mut buffer) {
+            assert_eq!(1, read.unwrap());
+            return Poll::Ready(());
+        }
+
+        if !self.written {
+            let written = unsafe { libc::write(self.pipe, buffer.as_ptr() as *const _, 1) };
+            assert_eq!(1, written);
+            self.written = true;
+        }
+
+        Poll::Pending
+    }
+}
+
+/// Run a benchmark with stdin swapped out for a pipe, passing it the write end of the pipe.
+fn with_piped_stdin(bench: impl FnOnce(&mut Executor, &mut Stdin, c_int)) {
+    let pipe = pipe_stdin();
+    let mut input = Stdin::new().unwrap();
+    let mut executor = Executor::new();
+
+    bench(&mut executor, &mut input, pipe);
+
+    drop(input);
+    unsafe {
+        libc::close(pipe);
+    }
+}
+
+/// How long it takes to get from an IO object becoming ready to the task waiting on it being
+/// polled again, including a spin of the reactor.
+fn io_to_poll(c: &mut Criterion) {
+    with_piped_stdin(|executor, input, pipe| {
+        c.bench_function("io to poll", |b| {
+            b.iter(|| executor.complete(RoundTrip::new(input, pipe)).unwrap())
+        });
+    });
+}
+
+/// A large events buffer, so that the cost of allocating it shows up clearly.
+const EVENTS_CAPACITY: usize = 1024;
+
+/// How much the reactor saves by keeping its events buffer between spins, compared to allocating
+/// a new one for every spin.
+fn events_buffer(c: &mut Criterion) {
+    with_piped_stdin(|executor, input, pipe| {
+        Reactor::set_events_capacity(EVENTS_CAPACITY);
+        c.bench_function("spin with a reused events buffer", |b| {
+            b.iter(|| executor.complete(RoundTrip::new(input, pipe)).unwrap())
+        });
+
+        c.bench_function("spin with a new events buffer", |b| {
+            b.iter(|| {
+                // every iteration spins exactly once, so this is one allocation per spin
+                Reactor::set_events_capacity(EVENTS_CAPACITY);
+                executor.complete(RoundTrip::new(input, pipe)).unwrap()
+            })
+        });
+    });
+}
+
+criterion_group!(benches, io_to_poll, events_buffer);
+criterion_main!(benches);
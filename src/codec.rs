@@ -0,0 +1,6 @@
+//! Codecs that give structure to the raw byte streams handled by the reactor.
+
+mod length_delimited;
+mod lines;
+
+pub use self::{length_delimited::LengthDelimited, lines::Lines};
@@ -0,0 +1,227 @@
+use std::{
+    io::{self, ErrorKind},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{
+    io::{AsyncRead, AsyncWrite},
+    ready,
+    sink::Sink,
+    stream::Stream,
+};
+
+/// The number of bytes used to encode the length of each frame.
+const HEADER_LEN: usize = 4;
+
+/// The largest frame that will be sent or accepted. Anything bigger than this is treated as a
+/// protocol error, rather than as an excuse to allocate an unbounded amount of memory.
+const MAX_FRAME_LEN: usize = 8 * 1024 * 1024;
+
+/// How much encoded data may be waiting to be written before the sink stops accepting frames.
+const HIGH_WATER_MARK: usize = 64 * 1024;
+
+/// A codec that splits a byte stream into discrete frames, by prefixing every frame with its length
+/// as a big endian 32 bit integer. Message boundaries survive however the underlying transport
+/// decides to coalesce or split up writes.
+pub struct LengthDelimited<T> {
+    /// The byte stream that frames are read from and written to.
+    inner: T,
+    /// Bytes read from the inner stream that have not yet been returned as part of a frame.
+    read_buffer: Vec<u8>,
+    /// Encoded frames that have not yet been written to the inner stream.
+    write_buffer: Vec<u8>,
+    /// Set once the incoming bytes can't be decoded. The stream can't find the start of the next
+    /// frame after that, so it ends rather than reporting the same error forever.
+    failed: bool,
+}
+
+impl<T> LengthDelimited<T> {
+    /// Wrap a byte stream in the codec.
+    pub fn new(inner: T) -> Self {
+        LengthDelimited {
+            inner,
+            read_buffer: Vec::new(),
+            write_buffer: Vec::new(),
+            failed: false,
+        }
+    }
+
+    /// Try to take a complete frame off the front of the read buffer. Returns None if the buffer
+    /// does not yet hold a complete frame.
+    fn decode(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if self.read_buffer.len() < HEADER_LEN {
+            return Ok(None);
+        }
+
+        let mut header = [0; HEADER_LEN];
+        header.copy_from_slice(&self.read_buffer[..HEADER_LEN]);
+        let length = u32::from_be_bytes(header) as usize;
+
+        if length > MAX_FRAME_LEN {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Frame is larger than the maximum frame length",
+            ));
+        }
+
+        if self.read_buffer.len() < HEADER_LEN + length {
+            return Ok(None);
+        }
+
+        let frame = self.read_buffer[HEADER_LEN..HEADER_LEN + length].to_vec();
+        self.read_buffer.drain(..HEADER_LEN + length);
+
+        Ok(Some(frame))
+    }
+}
+
+impl<T: AsyncRead + Unpin> Stream for LengthDelimited<T> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let mut chunk = [0; 4096];
+
+        if this.failed {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            match this.decode() {
+                Ok(Some(frame)) => return Poll::Ready(Some(Ok(frame))),
+                Ok(None) => (),
+                Err(e) => {
+                    this.failed = true;
+                    this.read_buffer.clear();
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+
+            let read = match ready!(Pin::new(&mut this.inner).poll_read(ctx, &mut chunk)) {
+                Ok(read) => read,
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            };
+
+            if read == 0 {
+                if this.read_buffer.is_empty() {
+                    return Poll::Ready(None);
+                }
+
+                // don't report the same partial frame again if we get polled after this
+                this.read_buffer.clear();
+                return Poll::Ready(Some(Err(io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "Stream ended part way through a frame",
+                ))));
+            }
+
+            this.read_buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+}
+
+impl<T: AsyncWrite + Unpin> Sink<Vec<u8>> for LengthDelimited<T> {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<io::Result<()>> {
+        if self.write_buffer.len() < HIGH_WATER_MARK {
+            Poll::Ready(Ok(()))
+        } else {
+            self.poll_flush(ctx)
+        }
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, frame: Vec<u8>) -> io::Result<()> {
+        if frame.len() > MAX_FRAME_LEN {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "Frame is larger than the maximum frame length",
+            ));
+        }
+
+        let header = (frame.len() as u32).to_be_bytes();
+        self.write_buffer.extend_from_slice(&header);
+        self.write_buffer.extend_from_slice(&frame);
+
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+
+        while !this.write_buffer.is_empty() {
+            let written = ready!(Pin::new(&mut this.inner).poll_write(ctx, &this.write_buffer))?;
+            if written == 0 {
+                return Poll::Ready(Err(ErrorKind::WriteZero.into()));
+            }
+            this.write_buffer.drain(..written);
+        }
+
+        Pin::new(&mut this.inner).poll_flush(ctx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush(ctx))?;
+        Pin::new(&mut self.inner).poll_close(ctx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::ErrorKind;
+
+    use futures::{executor, io::Cursor, SinkExt, StreamExt};
+
+    use crate::codec::LengthDelimited;
+
+    #[test]
+    fn round_trip() {
+        let future = async {
+            let mut output = LengthDelimited::new(Cursor::new(Vec::new()));
+            output.send(b"Hello".to_vec()).await.unwrap();
+            output.send(Vec::new()).await.unwrap();
+            output.send(b", world".to_vec()).await.unwrap();
+
+            let bytes = output.inner.into_inner();
+            assert_eq!(4 + 5 + 4 + 4 + 7, bytes.len());
+
+            let frames = LengthDelimited::new(Cursor::new(bytes))
+                .map(Result::unwrap)
+                .collect::<Vec<_>>()
+                .await;
+
+            assert_eq!(vec![b"Hello".to_vec(), vec![], b", world".to_vec()], frames);
+        };
+
+        executor::block_on(future);
+    }
+
+    #[test]
+    fn truncated_frame() {
+        let future = async {
+            let mut input = LengthDelimited::new(Cursor::new(vec![0, 0, 0, 5, b'a', b'b']));
+
+            let error = input.next().await.unwrap().unwrap_err();
+            assert_eq!(ErrorKind::UnexpectedEof, error.kind());
+            assert!(input.next().await.is_none());
+        };
+
+        executor::block_on(future);
+    }
+
+    #[test]
+    fn oversized_frame() {
+        let future = async {
+            // the bytes after the bad header can't be trusted to be the start of another frame
+            let mut input =
+                LengthDelimited::new(Cursor::new(vec![0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0]));
+
+            let error = input.next().await.unwrap().unwrap_err();
+            assert_eq!(ErrorKind::InvalidData, error.kind());
+            assert!(input.next().await.is_none());
+        };
+
+        executor::block_on(future);
+    }
+}
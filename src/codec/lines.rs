@@ -0,0 +1,175 @@
+use std::{
+    io::{self, ErrorKind},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{io::AsyncRead, ready, stream::Stream};
+
+/// The longest line that will be accepted, including its newline. Anything longer than this is
+/// treated as an error, rather than as an excuse to buffer an unbounded amount of memory.
+const MAX_LINE_LEN: usize = 64 * 1024;
+
+/// A stream adapter that splits the bytes read from an inner stream into lines. Every line is
+/// returned with its trailing newline intact, so that the output can be reassembled exactly. The
+/// last line of the stream is returned without a newline if the stream did not end with one.
+pub struct Lines<T> {
+    /// The byte stream that lines are read from.
+    inner: T,
+    /// Bytes read from the inner stream that have not yet been returned as part of a line.
+    buffer: Vec<u8>,
+    /// How much of the buffer is already known not to contain a newline.
+    scanned: usize,
+    /// Set once a line has been too long. The stream can't tell where the next line starts after
+    /// that, so it ends rather than returning part of a line.
+    failed: bool,
+}
+
+impl<T> Lines<T> {
+    /// Wrap a byte stream in the adapter.
+    pub fn new(inner: T) -> Self {
+        Lines {
+            inner,
+            buffer: Vec::new(),
+            scanned: 0,
+            failed: false,
+        }
+    }
+
+    /// Try to take a complete line off the front of the buffer. Returns None if the buffer does
+    /// not yet hold a newline.
+    fn take_line(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let newline = self.buffer[self.scanned..]
+            .iter()
+            .position(|&byte| byte == b'\n');
+
+        let end = match newline {
+            Some(index) => self.scanned + index + 1,
+            None => {
+                self.scanned = self.buffer.len();
+                if self.scanned > MAX_LINE_LEN {
+                    return Err(line_too_long());
+                }
+                return Ok(None);
+            }
+        };
+
+        if end > MAX_LINE_LEN {
+            return Err(line_too_long());
+        }
+
+        self.scanned = 0;
+        Ok(Some(self.buffer.drain(..end).collect()))
+    }
+}
+
+/// The error returned for lines longer than the maximum line length.
+fn line_too_long() -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        "Line is longer than the maximum line length",
+    )
+}
+
+impl<T: AsyncRead + Unpin> Stream for Lines<T> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let mut chunk = [0; 4096];
+
+        if this.failed {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            match this.take_line() {
+                Ok(Some(line)) => return Poll::Ready(Some(Ok(line))),
+                Ok(None) => (),
+                Err(e) => {
+                    this.failed = true;
+                    this.buffer.clear();
+                    this.scanned = 0;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+
+            let read = match ready!(Pin::new(&mut this.inner).poll_read(ctx, &mut chunk)) {
+                Ok(read) => read,
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            };
+
+            if read == 0 {
+                if this.buffer.is_empty() {
+                    return Poll::Ready(None);
+                }
+
+                this.scanned = 0;
+                let line = this.buffer.drain(..).collect();
+                return Poll::Ready(Some(Ok(line)));
+            }
+
+            this.buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::ErrorKind;
+
+    use futures::{executor, io::Cursor, StreamExt};
+
+    use crate::codec::{lines::MAX_LINE_LEN, Lines};
+
+    #[test]
+    fn split_lines() {
+        let future = async {
+            let input = Cursor::new(b"Hello, world\n\nGoodbye for now".to_vec());
+            let lines = Lines::new(input)
+                .map(Result::unwrap)
+                .collect::<Vec<_>>()
+                .await;
+
+            assert_eq!(
+                vec![
+                    b"Hello, world\n".to_vec(),
+                    b"\n".to_vec(),
+                    b"Goodbye for now".to_vec(),
+                ],
+                lines
+            );
+        };
+
+        executor::block_on(future);
+    }
+
+    #[test]
+    fn line_across_reads() {
+        let future = async {
+            let mut line = vec![b'a'; 10_000];
+            line.push(b'\n');
+            let mut input = Lines::new(Cursor::new(line.clone()));
+
+            assert_eq!(line, input.next().await.unwrap().unwrap());
+            assert!(input.next().await.is_none());
+        };
+
+        executor::block_on(future);
+    }
+
+    #[test]
+    fn line_too_long() {
+        let future = async {
+            let mut bytes = vec![b'a'; MAX_LINE_LEN];
+            bytes.extend_from_slice(b"\nshort\n");
+            let mut input = Lines::new(Cursor::new(bytes));
+
+            let error = input.next().await.unwrap().unwrap_err();
+            assert_eq!(ErrorKind::InvalidData, error.kind());
+            assert!(input.next().await.is_none());
+        };
+
+        executor::block_on(future);
+    }
+}
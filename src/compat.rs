@@ -0,0 +1,147 @@
+//! Adapters between the IO traits from the futures crate, which this runtime is built on, and the
+//! IO traits from tokio, so that libraries written against tokio can be used here.
+
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::io as fut;
+use tokio::io as tok;
+
+/// Wraps an IO object that implements one set of IO traits, so that it implements the other set.
+/// An object implementing the futures traits is given the tokio traits, and an object
+/// implementing the tokio traits is given the futures traits.
+#[derive(Debug)]
+pub struct Compat<T> {
+    inner: T,
+}
+
+impl<T> Compat<T> {
+    /// Wrap an IO object in the adapter.
+    pub fn new(inner: T) -> Self {
+        Compat { inner }
+    }
+
+    /// Get a reference to the wrapped IO object.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped IO object.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the IO object from the adapter.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: fut::AsyncRead + Unpin> tok::AsyncRead for Compat<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        ctx: &mut Context,
+        buffer: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        fut::AsyncRead::poll_read(Pin::new(&mut self.inner), ctx, buffer)
+    }
+}
+
+impl<T: fut::AsyncBufRead + Unpin> tok::AsyncBufRead for Compat<T> {
+    fn poll_fill_buf(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<io::Result<&[u8]>> {
+        fut::AsyncBufRead::poll_fill_buf(Pin::new(&mut self.get_mut().inner), ctx)
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amount: usize) {
+        fut::AsyncBufRead::consume(Pin::new(&mut self.inner), amount)
+    }
+}
+
+impl<T: fut::AsyncWrite + Unpin> tok::AsyncWrite for Compat<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        ctx: &mut Context,
+        buffer: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        fut::AsyncWrite::poll_write(Pin::new(&mut self.inner), ctx, buffer)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<io::Result<()>> {
+        fut::AsyncWrite::poll_flush(Pin::new(&mut self.inner), ctx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<io::Result<()>> {
+        fut::AsyncWrite::poll_close(Pin::new(&mut self.inner), ctx)
+    }
+}
+
+impl<T: tok::AsyncRead + Unpin> fut::AsyncRead for Compat<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        ctx: &mut Context,
+        buffer: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        tok::AsyncRead::poll_read(Pin::new(&mut self.inner), ctx, buffer)
+    }
+}
+
+impl<T: tok::AsyncBufRead + Unpin> fut::AsyncBufRead for Compat<T> {
+    fn poll_fill_buf(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<io::Result<&[u8]>> {
+        tok::AsyncBufRead::poll_fill_buf(Pin::new(&mut self.get_mut().inner), ctx)
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amount: usize) {
+        tok::AsyncBufRead::consume(Pin::new(&mut self.inner), amount)
+    }
+}
+
+impl<T: tok::AsyncWrite + Unpin> fut::AsyncWrite for Compat<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        ctx: &mut Context,
+        buffer: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        tok::AsyncWrite::poll_write(Pin::new(&mut self.inner), ctx, buffer)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<io::Result<()>> {
+        tok::AsyncWrite::poll_flush(Pin::new(&mut self.inner), ctx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<io::Result<()>> {
+        tok::AsyncWrite::poll_shutdown(Pin::new(&mut self.inner), ctx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::{
+        executor,
+        io::{AsyncReadExt, AsyncWriteExt, Cursor},
+    };
+
+    use crate::compat::Compat;
+
+    #[test]
+    fn round_trip() {
+        // the inner adapter turns the futures cursor into a tokio object, and the outer adapter
+        // turns it back again
+        let mut stream = Compat::new(Compat::new(Cursor::new(b"Hello, world".to_vec())));
+
+        let future = async {
+            let mut buffer = String::new();
+            stream.read_to_string(&mut buffer).await.unwrap();
+            assert_eq!("Hello, world", buffer);
+
+            stream.write_all(b"\nGoodbye for now").await.unwrap();
+            stream.close().await.unwrap();
+        };
+        executor::block_on(future);
+
+        let output = stream.into_inner().into_inner().into_inner();
+        assert_eq!(b"Hello, world\nGoodbye for now", &output[..]);
+    }
+}
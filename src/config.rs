@@ -1,8 +1,14 @@
-use env_logger::Builder;
+use std::path::PathBuf;
+
 use log::LevelFilter;
-use structopt::StructOpt;
+use structopt::{
+    clap::{self, ErrorKind},
+    StructOpt,
+};
 use url::Url;
 
+use crate::logging::{self, Format, Style, Target, Timestamps};
+
 /// Command line arguments given to the process.
 #[derive(StructOpt)]
 pub struct Arguments {
@@ -11,6 +17,19 @@ pub struct Arguments {
     /// How verbosely to log.
     #[structopt(short, long, parse(from_occurrences))]
     verbose: u8,
+    /// Prefix every logged line with a timestamp: the time in UTC, the local time, or the time
+    /// elapsed since startup.
+    #[structopt(long, possible_values = &["utc", "local", "delta"])]
+    timestamps: Option<Timestamps>,
+    /// How to format diagnostic output: human readable text, or one JSON object per line.
+    #[structopt(long, default_value = "text", possible_values = &["text", "json"])]
+    log_format: Format,
+    /// Append diagnostic output to this file, instead of writing it to stderr.
+    #[structopt(long, parse(from_os_str), conflicts_with = "log-syslog")]
+    log_file: Option<PathBuf>,
+    /// Send diagnostic output to the system logger, instead of writing it to stderr.
+    #[structopt(long)]
+    log_syslog: bool,
 }
 
 impl Arguments {
@@ -32,10 +51,19 @@ impl Arguments {
     pub fn new() -> Self {
         let args = Arguments::from_args();
 
-        let mut logger = Builder::new();
-        logger.filter_level(Self::log_level(args.verbose));
-        logger.filter_module("nt", Self::log_level(args.verbose + 1));
-        logger.init();
+        let style = Style::new(args.log_format, args.timestamps);
+        let target = match &args.log_file {
+            Some(path) => Target::File(path),
+            None if args.log_syslog => Target::Syslog,
+            None => Target::Stderr,
+        };
+
+        let level = Self::log_level(args.verbose);
+        let own_level = Self::log_level(args.verbose + 1);
+        if let Err(e) = logging::init(level, own_level, style, target) {
+            let message = format!("Could not open the log file: {}", e);
+            clap::Error::with_description(&message, ErrorKind::Io).exit();
+        }
 
         args
     }
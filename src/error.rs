@@ -0,0 +1,58 @@
+use std::{error, fmt, io};
+
+use trust_dns_resolver::error::ResolveError;
+
+/// Everything that can go wrong in net-tiger. Each kind of failure gets its own variant, so that
+/// callers can tell them apart without having to inspect error messages. More variants will be
+/// added as net-tiger learns new ways to fail, so matches on this need a catch-all arm.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An IO operation failed.
+    Io(io::Error),
+    /// A hostname could not be resolved.
+    Dns(ResolveError),
+    /// A TLS session could not be established or maintained.
+    Tls(String),
+    /// An operation did not complete in the time it was allowed.
+    Timeout,
+    /// A proxy refused, or failed to set up, the connection that it was asked for.
+    Proxy(String),
+    /// The remote end broke the rules of the protocol being spoken.
+    Protocol(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "IO error: {}", e),
+            Error::Dns(e) => write!(f, "DNS error: {}", e),
+            Error::Tls(message) => write!(f, "TLS error: {}", message),
+            Error::Timeout => write!(f, "Timed out"),
+            Error::Proxy(message) => write!(f, "Proxy error: {}", message),
+            Error::Protocol(message) => write!(f, "Protocol error: {}", message),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Dns(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<ResolveError> for Error {
+    fn from(e: ResolveError) -> Self {
+        Error::Dns(e)
+    }
+}
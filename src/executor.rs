@@ -3,17 +3,16 @@ use std::{
     marker::PhantomData,
     mem::MaybeUninit,
     pin::Pin,
-    sync::mpsc::{self, Receiver, SyncSender, TryRecvError},
     task::{self, Context, Poll},
 };
 
-use failure::Error;
 use slab::Slab;
 
+mod queue;
 mod waker;
 
-use self::waker::Waker;
-use crate::reactor::Reactor;
+use self::{queue::WakeQueue, waker::Waker};
+use crate::{error::Error, reactor::Reactor};
 
 type Task<'a, T> = Pin<Box<dyn 'a + Future<Output = T>>>;
 
@@ -21,8 +20,8 @@ type Task<'a, T> = Pin<Box<dyn 'a + Future<Output = T>>>;
 /// be run. The executor is the entrypoint to the runtime, and wraps all other parts of the
 /// runtime.
 pub struct Executor {
-    to_do: Receiver<usize>,
-    send_handle: SyncSender<usize>,
+    /// The tasks that have been woken, and need to be polled again.
+    to_do: WakeQueue,
     tasks: Slab<MaybeUninit<(Task<'static, ()>, task::Waker)>>,
     /// In functions such as complete, we need to be able to have a separate task (that is not
     /// static, and returns a value) that is also handled by the executor. Reserve an ID in the
@@ -36,7 +35,7 @@ pub struct Executor {
 impl Executor {
     /// Create a new executor.
     pub fn new() -> Self {
-        let (send_handle, to_do) = mpsc::sync_channel(64);
+        let to_do = WakeQueue::new();
         let mut tasks = Slab::new();
 
         // make sure that we don't just go straight to the slab when we need new things
@@ -49,7 +48,6 @@ impl Executor {
 
         Executor {
             to_do,
-            send_handle,
             tasks,
             separate_task,
             reactor,
@@ -62,7 +60,7 @@ impl Executor {
         let future = Box::pin(future) as Task<'static, ()>;
         let space = self.tasks.vacant_entry();
         let waker = Waker {
-            sender: self.send_handle.clone(),
+            queue: self.to_do.clone(),
             id: space.key(),
         }
         .to_waker();
@@ -75,7 +73,7 @@ impl Executor {
     pub fn complete<'a, T>(&mut self, future: impl 'a + Future<Output = T>) -> Result<T, Error> {
         let mut main_future = Box::pin(future) as Task<'a, T>;
         let waker = Waker {
-            sender: self.send_handle.clone(),
+            queue: self.to_do.clone(),
             id: self.separate_task,
         }
         .to_waker();
@@ -115,10 +113,9 @@ impl Executor {
 
         let output = loop {
             log::trace!("Looking in backlog for futures");
-            let future_to_poll = match self.to_do.try_recv() {
-                Ok(id) => id,
-                Err(TryRecvError::Disconnected) => unreachable!(),
-                Err(TryRecvError::Empty) => {
+            let future_to_poll = match self.to_do.pop() {
+                Some(id) => id,
+                None => {
                     Reactor::spin()?;
                     continue;
                 }
@@ -131,11 +128,21 @@ impl Executor {
                     break result;
                 }
             } else {
+                // a task can be woken more than once before it is polled, and finish on the
+                // first of those polls
+                let task = match self.tasks.get_mut(future_to_poll) {
+                    Some(task) => task,
+                    None => {
+                        log::trace!("Future {} has already finished", future_to_poll);
+                        continue;
+                    }
+                };
+
                 log::trace!("Polling future {}", future_to_poll);
                 // we know that future is a valid value as long as the ID isn't
                 // self.separate_task, as that is the only ID in the slab associated
                 // with an uninitialised value.
-                let (future, waker) = unsafe { &mut *self.tasks[future_to_poll].as_mut_ptr() };
+                let (future, waker) = unsafe { &mut *task.as_mut_ptr() };
                 let mut ctx = Context::from_waker(&waker);
                 if let Poll::Ready(()) = future.as_mut().poll(&mut ctx) {
                     self.tasks.remove(future_to_poll);
@@ -147,6 +154,12 @@ impl Executor {
     }
 }
 
+impl Default for Executor {
+    fn default() -> Self {
+        Executor::new()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::{
@@ -0,0 +1,35 @@
+use std::{collections::VecDeque, fmt};
+
+use crate::sync::{Arc, Mutex};
+
+/// The queue of tasks that have been woken, and are waiting to be polled by the executor. Wakers
+/// can be sent to other threads, so tasks can be pushed onto the queue from anywhere.
+#[derive(Clone)]
+pub struct WakeQueue {
+    inner: Arc<Mutex<VecDeque<usize>>>,
+}
+
+impl WakeQueue {
+    /// Create a new, empty queue.
+    pub fn new() -> Self {
+        let inner = Arc::new(Mutex::new(VecDeque::new()));
+
+        WakeQueue { inner }
+    }
+
+    /// Add the ID of a woken task to the back of the queue.
+    pub fn push(&self, id: usize) {
+        self.inner.lock().unwrap().push_back(id);
+    }
+
+    /// Take the ID of the task at the front of the queue, if there is one.
+    pub fn pop(&self) -> Option<usize> {
+        self.inner.lock().unwrap().pop_front()
+    }
+}
+
+impl fmt::Debug for WakeQueue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WakeQueue").finish()
+    }
+}
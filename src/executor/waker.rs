@@ -1,13 +1,12 @@
-use std::{
-    sync::mpsc::SyncSender,
-    task::{self, RawWaker, RawWakerVTable},
-};
+use std::task::{self, RawWaker, RawWakerVTable};
+
+use super::queue::WakeQueue;
 
 /// An implementation of the Waker interface used in asynchronous runtimes.
 #[derive(Debug, Clone)]
 pub struct Waker {
-    /// The channel through which to send notification events.
-    pub sender: SyncSender<usize>,
+    /// The queue to push this task onto when it is woken.
+    pub queue: WakeQueue,
     /// The ID of the task associated with this particular waker.
     pub id: usize,
 }
@@ -58,6 +57,68 @@ impl Waker {
     /// Actually wake the waker.
     fn do_wake(&self) {
         log::trace!("Waking task {}", self.id);
-        self.sender.send(self.id).unwrap();
+        self.queue.push(self.id);
+    }
+}
+
+#[cfg(all(test, loom))]
+mod test {
+    use loom::thread;
+
+    use crate::executor::{queue::WakeQueue, waker::Waker};
+
+    #[test]
+    fn wake_from_another_thread() {
+        loom::model(|| {
+            let queue = WakeQueue::new();
+            let waker = Waker {
+                queue: queue.clone(),
+                id: 1,
+            }
+            .to_waker();
+
+            let other = waker.clone();
+            let thread = thread::spawn(move || other.wake());
+
+            waker.wake_by_ref();
+            drop(waker);
+            thread.join().unwrap();
+
+            assert_eq!(Some(1), queue.pop());
+            assert_eq!(Some(1), queue.pop());
+            assert_eq!(None, queue.pop());
+        });
+    }
+
+    #[test]
+    fn clone_and_drop_on_many_threads() {
+        loom::model(|| {
+            let queue = WakeQueue::new();
+            let waker = Waker {
+                queue: queue.clone(),
+                id: 2,
+            }
+            .to_waker();
+
+            let threads = (0..2usize)
+                .map(|_| {
+                    let waker = waker.clone();
+                    thread::spawn(move || {
+                        let copy = waker.clone();
+                        drop(waker);
+                        copy.wake();
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            drop(waker);
+            threads
+                .into_iter()
+                .for_each(|thread| thread.join().unwrap());
+
+            assert_eq!(Some(2), queue.pop());
+            assert_eq!(Some(2), queue.pop());
+            assert_eq!(None, queue.pop());
+        });
     }
 }
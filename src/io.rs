@@ -0,0 +1,239 @@
+//! Helpers for moving data between the IO types in the runtime.
+
+use std::{
+    future::Future,
+    io::{self, ErrorKind},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{
+    io::{AsyncRead, AsyncWrite},
+    ready,
+};
+
+/// Copy data in both directions between two streams at the same time, until both of them have
+/// reached the end of their input. Whenever one stream runs out of input, the other is closed for
+/// writing, so that half-closed connections are passed along. Resolves to the number of bytes
+/// copied from `a` to `b`, and from `b` to `a`.
+pub fn copy_bidirectional<'a, A, B>(a: &'a mut A, b: &'a mut B) -> CopyBidirectional<'a, A, B>
+where
+    A: AsyncRead + AsyncWrite + Unpin,
+    B: AsyncRead + AsyncWrite + Unpin,
+{
+    CopyBidirectional {
+        a,
+        b,
+        a_to_b: Transfer::new(),
+        b_to_a: Transfer::new(),
+    }
+}
+
+/// A future that copies data in both directions between two streams. Created by
+/// `copy_bidirectional`.
+pub struct CopyBidirectional<'a, A, B> {
+    a: &'a mut A,
+    b: &'a mut B,
+    a_to_b: Transfer,
+    b_to_a: Transfer,
+}
+
+impl<A, B> Future for CopyBidirectional<'_, A, B>
+where
+    A: AsyncRead + AsyncWrite + Unpin,
+    B: AsyncRead + AsyncWrite + Unpin,
+{
+    type Output = io::Result<(u64, u64)>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        let a_to_b = match this.a_to_b.poll_copy(ctx, &mut *this.a, &mut *this.b) {
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Ready(Ok(amount)) => Some(amount),
+            Poll::Pending => None,
+        };
+        let b_to_a = match this.b_to_a.poll_copy(ctx, &mut *this.b, &mut *this.a) {
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Ready(Ok(amount)) => Some(amount),
+            Poll::Pending => None,
+        };
+
+        match (a_to_b, b_to_a) {
+            (Some(a_to_b), Some(b_to_a)) => Poll::Ready(Ok((a_to_b, b_to_a))),
+            _ => Poll::Pending,
+        }
+    }
+}
+
+/// The state of copying data in one direction.
+struct Transfer {
+    /// Data that has been read, but not yet written.
+    buffer: Box<[u8]>,
+    /// The position in the buffer of the first byte that has not been written.
+    pos: usize,
+    /// The position in the buffer just past the last byte that was read.
+    filled: usize,
+    /// Whether anything has been written since the writer was last flushed.
+    needs_flush: bool,
+    /// Whether the reader has run out of data.
+    read_done: bool,
+    /// Whether the writer has been closed, which means this transfer is finished.
+    closed: bool,
+    /// The number of bytes copied so far.
+    amount: u64,
+}
+
+impl Transfer {
+    /// The size of the buffer used for each direction.
+    const CAPACITY: usize = 8 * 1024;
+
+    fn new() -> Self {
+        Transfer {
+            buffer: vec![0; Self::CAPACITY].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+            needs_flush: false,
+            read_done: false,
+            closed: false,
+            amount: 0,
+        }
+    }
+
+    /// Copy as much data as is available from the reader to the writer. Resolves to the total
+    /// number of bytes copied once the reader has run out and the writer has been closed.
+    fn poll_copy<R, W>(
+        &mut self,
+        ctx: &mut Context,
+        reader: &mut R,
+        writer: &mut W,
+    ) -> Poll<io::Result<u64>>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut reader = Pin::new(reader);
+        let mut writer = Pin::new(writer);
+
+        loop {
+            if self.closed {
+                return Poll::Ready(Ok(self.amount));
+            }
+
+            if self.pos == self.filled && !self.read_done {
+                match reader.as_mut().poll_read(ctx, &mut self.buffer) {
+                    Poll::Ready(Ok(0)) => self.read_done = true,
+                    Poll::Ready(Ok(read)) => {
+                        self.pos = 0;
+                        self.filled = read;
+                    }
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Pending => {
+                        // don't leave data sitting in the writer while waiting for more
+                        if self.needs_flush {
+                            ready!(writer.as_mut().poll_flush(ctx))?;
+                            self.needs_flush = false;
+                        }
+                        return Poll::Pending;
+                    }
+                }
+            }
+
+            while self.pos < self.filled {
+                let written = ready!(writer
+                    .as_mut()
+                    .poll_write(ctx, &self.buffer[self.pos..self.filled]))?;
+                if written == 0 {
+                    return Poll::Ready(Err(ErrorKind::WriteZero.into()));
+                }
+                self.pos += written;
+                self.amount += written as u64;
+                self.needs_flush = true;
+            }
+
+            if self.read_done {
+                ready!(writer.as_mut().poll_close(ctx))?;
+                self.closed = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    use futures::{
+        executor,
+        io::{AsyncRead, AsyncWrite, Cursor},
+    };
+
+    use crate::io::copy_bidirectional;
+
+    /// One end of a connection, which has a fixed amount of data to send, and records everything
+    /// that it receives.
+    struct Peer {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+        closed: bool,
+    }
+
+    impl Peer {
+        fn new(input: &[u8]) -> Self {
+            Peer {
+                input: Cursor::new(input.to_vec()),
+                output: Vec::new(),
+                closed: false,
+            }
+        }
+    }
+
+    impl AsyncRead for Peer {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            ctx: &mut Context,
+            buffer: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.input).poll_read(ctx, buffer)
+        }
+    }
+
+    impl AsyncWrite for Peer {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _: &mut Context,
+            buffer: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            assert!(!self.closed, "Wrote to a closed peer");
+            self.output.extend_from_slice(buffer);
+            Poll::Ready(Ok(buffer.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(mut self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+            self.closed = true;
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn both_directions() {
+        let mut a = Peer::new(b"ping");
+        let mut b = Peer::new(b"pong!");
+
+        let amounts = executor::block_on(copy_bidirectional(&mut a, &mut b)).unwrap();
+
+        assert_eq!((4, 5), amounts);
+        assert_eq!(b"pong!", &a.output[..]);
+        assert_eq!(b"ping", &b.output[..]);
+        assert!(a.closed);
+        assert!(b.closed);
+    }
+}
@@ -0,0 +1,13 @@
+//! The asynchronous runtime behind net-tiger: a single threaded executor, the reactor that drives
+//! its IO, and the IO types and codecs built on top of them.
+
+pub mod codec;
+#[cfg(feature = "tokio")]
+pub mod compat;
+pub mod error;
+pub mod executor;
+pub mod io;
+pub mod reactor;
+mod sync;
+#[cfg(feature = "test-util")]
+pub mod test;
@@ -0,0 +1,500 @@
+//! Diagnostic output. Logs are filtered and formatted the same way wherever they are sent, so that
+//! switching from stderr to a file or to syslog doesn't change what ends up being written.
+
+use std::{
+    ffi::CString,
+    fmt::{self, Display, Write as _},
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    os::raw::c_char,
+    path::Path,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use env_logger::filter::{self, Filter};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// The styles of timestamp that can be attached to logged lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timestamps {
+    /// Wall clock time, in UTC.
+    Utc,
+    /// Wall clock time, in the local timezone.
+    Local,
+    /// Seconds elapsed since the process started.
+    Delta,
+}
+
+impl FromStr for Timestamps {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "utc" => Ok(Timestamps::Utc),
+            "local" => Ok(Timestamps::Local),
+            "delta" => Ok(Timestamps::Delta),
+            other => Err(format!("Unknown timestamp style {:?}", other)),
+        }
+    }
+}
+
+impl Timestamps {
+    /// Format the current time in this style.
+    fn now(self, start: Instant) -> String {
+        match self {
+            Timestamps::Utc => wall_clock(false),
+            Timestamps::Local => wall_clock(true),
+            Timestamps::Delta => elapsed(start.elapsed()),
+        }
+    }
+}
+
+/// The formats that diagnostic output can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Human readable lines of text.
+    Text,
+    /// One JSON object per line, for consumption by log pipelines.
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            other => Err(format!("Unknown log format {:?}", other)),
+        }
+    }
+}
+
+/// Where diagnostic output is sent.
+pub enum Target<'a> {
+    /// Standard error, which is where logs go by default.
+    Stderr,
+    /// A file, which is appended to.
+    File(&'a Path),
+    /// The system logger.
+    Syslog,
+}
+
+/// Everything needed to turn a log record into a line of output.
+#[derive(Debug, Clone, Copy)]
+pub struct Style {
+    format: Format,
+    timestamps: Option<Timestamps>,
+    /// When the process started, for elapsed time stamps.
+    start: Instant,
+}
+
+impl Style {
+    /// Create a new style, timing elapsed time stamps from now.
+    pub fn new(format: Format, timestamps: Option<Timestamps>) -> Self {
+        let start = Instant::now();
+
+        Style {
+            format,
+            timestamps,
+            start,
+        }
+    }
+
+    /// Write a single record, including its trailing newline. The level is passed separately so
+    /// that the caller can decide how to display it.
+    fn write(&self, out: &mut impl Write, level: impl Display, record: &Record) -> io::Result<()> {
+        let time = self.timestamps.map(|style| (style, style.now(self.start)));
+
+        match self.format {
+            Format::Text => {
+                write!(out, "[")?;
+                match time {
+                    Some((Timestamps::Delta, time)) => write!(out, "+{}s ", time)?,
+                    Some((_, time)) => write!(out, "{} ", time)?,
+                    None => (),
+                }
+                writeln!(out, "{} {}] {}", level, record.target(), record.args())
+            }
+            Format::Json => {
+                write!(out, "{{")?;
+                match time {
+                    Some((Timestamps::Delta, time)) => write!(out, "\"time\":{},", time)?,
+                    Some((_, time)) => write!(out, "\"time\":{},", JsonString(&time))?,
+                    None => (),
+                }
+                let message = record.args().to_string();
+                writeln!(
+                    out,
+                    "\"level\":\"{}\",\"target\":{},\"message\":{}}}",
+                    level,
+                    JsonString(record.target()),
+                    JsonString(&message),
+                )
+            }
+        }
+    }
+}
+
+/// A logger for the targets that env_logger can't write to by itself.
+struct Logger {
+    filter: Filter,
+    style: Style,
+    sink: Sink,
+}
+
+/// The non-stderr destinations for a logger.
+enum Sink {
+    File(Mutex<File>),
+    Syslog,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.filter.matches(record) {
+            return;
+        }
+
+        let mut line = Vec::new();
+        if self.style.write(&mut line, record.level(), record).is_err() {
+            return;
+        }
+
+        match &self.sink {
+            Sink::File(file) => {
+                // there is nowhere left to report a failure to log to
+                let _ = file.lock().unwrap().write_all(&line);
+            }
+            Sink::Syslog => {
+                // syslog adds its own line endings, and can't cope with embedded nul bytes
+                line.pop();
+                line.retain(|&byte| byte != 0);
+                let line = CString::new(line).unwrap();
+                unsafe {
+                    libc::syslog(
+                        syslog_priority(record.level()),
+                        b"%s\0".as_ptr() as *const c_char,
+                        line.as_ptr(),
+                    );
+                }
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Sink::File(file) = &self.sink {
+            let _ = file.lock().unwrap().flush();
+        }
+    }
+}
+
+/// The syslog priority that each log level is recorded with.
+fn syslog_priority(level: Level) -> libc::c_int {
+    match level {
+        Level::Error => libc::LOG_ERR,
+        Level::Warn => libc::LOG_WARNING,
+        Level::Info => libc::LOG_INFO,
+        Level::Debug | Level::Trace => libc::LOG_DEBUG,
+    }
+}
+
+/// The modules that make up this program, as opposed to its dependencies.
+const OWN_MODULES: [&str; 2] = ["nt", "net_tiger"];
+
+/// Set up the global logger. Records from this program are logged at `own_level`, and records from
+/// its dependencies at `level`. Fails if the log target could not be opened.
+pub fn init(
+    level: LevelFilter,
+    own_level: LevelFilter,
+    style: Style,
+    target: Target,
+) -> io::Result<()> {
+    let sink = match target {
+        Target::Stderr => {
+            let mut logger = env_logger::Builder::new();
+            logger.filter_level(level);
+            for module in &OWN_MODULES {
+                logger.filter_module(module, own_level);
+            }
+            logger.format(move |buf, record| match style.format {
+                // colours are only wanted by people reading the output, and would corrupt JSON
+                Format::Text => {
+                    let level = buf.default_styled_level(record.level());
+                    style.write(buf, level, record)
+                }
+                Format::Json => style.write(buf, record.level(), record),
+            });
+            logger.init();
+
+            return Ok(());
+        }
+        Target::File(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            Sink::File(Mutex::new(file))
+        }
+        Target::Syslog => {
+            unsafe {
+                libc::openlog(
+                    b"nt\0".as_ptr() as *const c_char,
+                    libc::LOG_PID,
+                    libc::LOG_USER,
+                );
+            }
+            Sink::Syslog
+        }
+    };
+
+    let mut filter = filter::Builder::new();
+    filter.filter_level(level);
+    for module in &OWN_MODULES {
+        filter.filter_module(module, own_level);
+    }
+    let filter = filter.build();
+
+    log::set_max_level(filter.filter());
+    let logger = Logger {
+        filter,
+        style,
+        sink,
+    };
+    log::set_boxed_logger(Box::new(logger)).expect("The logger was already set up");
+
+    Ok(())
+}
+
+/// Displays a string as a quoted and escaped JSON string.
+struct JsonString<'a>(&'a str);
+
+impl fmt::Display for JsonString<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+/// Format the current wall clock time as an RFC 3339 timestamp, either in the local timezone or in
+/// UTC. The time is given to the microsecond, so that it can be lined up with other logs.
+fn wall_clock(local: bool) -> String {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    let tm = unsafe {
+        let now = since_epoch.as_secs() as libc::time_t;
+        let mut tm = std::mem::zeroed::<libc::tm>();
+        if local {
+            libc::localtime_r(&now, &mut tm);
+        } else {
+            libc::gmtime_r(&now, &mut tm);
+        }
+        tm
+    };
+
+    rfc3339(&tm, since_epoch.subsec_micros())
+}
+
+/// Format a broken down time, plus some microseconds, as an RFC 3339 timestamp. Uses the UTC
+/// offset that the broken down time records.
+fn rfc3339(tm: &libc::tm, micros: u32) -> String {
+    let date = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec,
+        micros,
+    );
+
+    let offset = tm.tm_gmtoff / 60;
+    if offset == 0 {
+        format!("{}Z", date)
+    } else {
+        let sign = if offset < 0 { '-' } else { '+' };
+        format!(
+            "{}{}{:02}:{:02}",
+            date,
+            sign,
+            offset.abs() / 60,
+            offset.abs() % 60
+        )
+    }
+}
+
+/// Format an elapsed time as seconds, to the microsecond.
+fn elapsed(time: Duration) -> String {
+    format!("{}.{:06}", time.as_secs(), time.subsec_micros())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        env,
+        fs::{self, File},
+        path::Path,
+        process,
+        time::Duration,
+    };
+
+    use log::{Level, LevelFilter, Record};
+    use rusty_fork::{fork, rusty_fork_id, ChildWrapper};
+
+    use crate::logging::{self, Format, JsonString, Style, Target, Timestamps};
+
+    /// A broken down time of 2019-11-05 09:08:07, at the given offset from UTC in seconds.
+    fn tm(offset: i32) -> libc::tm {
+        let mut tm = unsafe { std::mem::zeroed::<libc::tm>() };
+        tm.tm_year = 119;
+        tm.tm_mon = 10;
+        tm.tm_mday = 5;
+        tm.tm_hour = 9;
+        tm.tm_min = 8;
+        tm.tm_sec = 7;
+        tm.tm_gmtoff = offset.into();
+        tm
+    }
+
+    #[test]
+    fn utc_timestamp() {
+        assert_eq!("2019-11-05T09:08:07.000250Z", logging::rfc3339(&tm(0), 250));
+    }
+
+    #[test]
+    fn local_timestamp() {
+        assert_eq!(
+            "2019-11-05T09:08:07.000000+09:30",
+            logging::rfc3339(&tm(9 * 3600 + 30 * 60), 0)
+        );
+        assert_eq!(
+            "2019-11-05T09:08:07.999999-05:00",
+            logging::rfc3339(&tm(-5 * 3600), 999_999)
+        );
+    }
+
+    #[test]
+    fn delta_timestamp() {
+        assert_eq!("0.000000", logging::elapsed(Duration::from_secs(0)));
+        assert_eq!(
+            "12.000250",
+            logging::elapsed(Duration::from_micros(12_000_250))
+        );
+
+        let style = Style::new(Format::Text, Some(Timestamps::Delta));
+
+        let mut output = Vec::new();
+        style
+            .write(
+                &mut output,
+                Level::Warn,
+                &Record::builder()
+                    .level(Level::Warn)
+                    .target("nt::test")
+                    .args(format_args!("hi"))
+                    .build(),
+            )
+            .unwrap();
+
+        // the elapsed time itself changes from run to run, so only check its shape
+        let output = String::from_utf8(output).unwrap();
+        let suffix = "s WARN nt::test] hi\n";
+        assert!(output.starts_with("[+"));
+        assert!(output.ends_with(suffix));
+        let time = &output[2..output.len() - suffix.len()];
+        let (seconds, micros) = time.split_at(time.find('.').unwrap());
+        assert!(seconds.chars().all(|c| c.is_ascii_digit()));
+        assert_eq!(7, micros.len());
+    }
+
+    #[test]
+    fn json_escaping() {
+        let input = "say \"hi\"\\\n\u{1b}";
+        assert_eq!(r#""say \"hi\"\\\n\u001b""#, JsonString(input).to_string());
+    }
+
+    #[test]
+    fn json_record() {
+        let style = Style::new(Format::Json, None);
+
+        let mut output = Vec::new();
+        style
+            .write(
+                &mut output,
+                Level::Warn,
+                &Record::builder()
+                    .level(Level::Warn)
+                    .target("nt::test")
+                    .args(format_args!("line one\nline two"))
+                    .build(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            "{\"level\":\"WARN\",\"target\":\"nt::test\",\"message\":\"line one\\nline two\"}\n",
+            String::from_utf8(output).unwrap()
+        );
+    }
+
+    #[test]
+    fn file_target() {
+        // the child process can't see this path otherwise, as it runs the test from the top
+        const PATH_VAR: &str = "NT_LOG_FILE_TEST_PATH";
+
+        fn child() {
+            let path = env::var_os(PATH_VAR).unwrap();
+            let style = Style::new(Format::Text, None);
+            logging::init(
+                LevelFilter::Warn,
+                LevelFilter::Debug,
+                style,
+                Target::File(Path::new(&path)),
+            )
+            .unwrap();
+
+            log::debug!(target: "nt::test", "kept");
+            log::debug!(target: "mio::poll", "filtered out");
+            log::warn!(target: "mio::poll", "also kept");
+            log::logger().flush();
+        }
+
+        let path = env::temp_dir().join(format!("nt-log-test-{}", process::id()));
+
+        fork(
+            "logging::test::file_target",
+            rusty_fork_id!(),
+            |cmd| {
+                cmd.env(PATH_VAR, &path);
+            },
+            |child: &mut ChildWrapper, _: &mut File| {
+                let status = child.wait().unwrap();
+                assert!(status.success());
+
+                let output = fs::read_to_string(&path).unwrap();
+                fs::remove_file(&path).unwrap();
+                assert_eq!(
+                    "[DEBUG nt::test] kept\n[WARN mio::poll] also kept\n",
+                    output
+                );
+            },
+            child,
+        )
+        .unwrap();
+    }
+}
@@ -1,6 +1,5 @@
 mod config;
-mod executor;
-mod reactor;
+mod logging;
 
 use self::config::Arguments;
 
@@ -1,12 +1,18 @@
-use std::{cell::RefCell, marker::PhantomData, task::Waker};
-
-use failure::Error;
-use mio::{Evented, Events, Poll, PollOpt, Ready, Token};
+use std::{
+    cell::{Cell, RefCell},
+    io,
+    marker::PhantomData,
+    task::Waker,
+};
+
+use mio::{unix::UnixReady, Evented, Events, Poll, PollOpt, Ready, Token};
 use slab::Slab;
 
+mod buf_stdin;
 mod stdin;
 
-pub use self::stdin::Stdin;
+pub use self::{buf_stdin::BufStdin, stdin::Stdin};
+use crate::error::Error;
 
 /// The reactor - part of the asynchronous runtime responsible for managing the pauses between IO
 /// tasks, and waking the tasks that are ready to be run after the pauses are complete.
@@ -16,16 +22,32 @@ pub struct Reactor {
     inner: Poll,
     /// Each IO item in the poll is given an unsigned integer token. This maps from the tokens to
     /// waker objects that can be used to notify associated tasks when they are ready.
-    tokens: RefCell<Slab<Vec<Waker>>>,
+    tokens: RefCell<Slab<Wakers>>,
+    /// Buffer that IO events are read into while spinning. Kept between spins so that it doesn't
+    /// have to be reallocated every time the reactor wakes up.
+    events: RefCell<Events>,
+    /// How readiness is reported for IO objects that don't ask for anything in particular.
+    trigger: Cell<Trigger>,
 }
 
 impl Reactor {
+    /// The number of IO events that can be handled in a single spin of the reactor, unless
+    /// configured otherwise.
+    const DEFAULT_EVENTS_CAPACITY: usize = 32;
+
     /// Create a new instance of the reactor, ready to be linked to IO objects.
     fn new() -> Result<Self, Error> {
         let inner = Poll::new()?;
         let tokens = RefCell::new(Slab::new());
+        let events = RefCell::new(Events::with_capacity(Self::DEFAULT_EVENTS_CAPACITY));
+        let trigger = Cell::new(Trigger::Edge);
 
-        let output = Reactor { inner, tokens };
+        let output = Reactor {
+            inner,
+            tokens,
+            events,
+            trigger,
+        };
 
         Ok(output)
     }
@@ -34,15 +56,36 @@ impl Reactor {
     /// with this reactor are ready to be polled again.
     fn spin_(&self) -> Result<(), Error> {
         log::trace!("Spinning");
-        let mut events = Events::with_capacity(32);
+        let mut events = self.events.borrow_mut();
 
         self.inner.poll(&mut events, None)?;
 
-        events.into_iter().for_each(|event| {
+        events.iter().for_each(|event| {
             let Token(token) = event.token();
-            self.tokens.borrow()[token]
-                .iter()
-                .for_each(Waker::wake_by_ref);
+            let ready = UnixReady::from(event.readiness());
+            // a closed or broken IO object won't become ready again, so wake everything waiting on
+            // it to let the tasks find out for themselves
+            let closed = ready.is_hup() || ready.is_error();
+
+            let (read, write) = match self.tokens.borrow_mut().get_mut(token) {
+                Some(wakers) => {
+                    let read = if ready.is_readable() || closed {
+                        wakers.read.take()
+                    } else {
+                        None
+                    };
+                    let write = if ready.is_writable() || closed {
+                        wakers.write.take()
+                    } else {
+                        None
+                    };
+                    (read, write)
+                }
+                // the handle was dropped after the event came in
+                None => return,
+            };
+
+            read.into_iter().chain(write).for_each(Waker::wake);
         });
 
         Ok(())
@@ -53,6 +96,57 @@ impl Reactor {
     pub fn spin() -> Result<(), Error> {
         REACTOR.with(Reactor::spin_)
     }
+
+    /// Set how many IO events the reactor of this thread can handle in a single spin. Any events
+    /// beyond this are picked up by the next spin instead. The reactor has to be able to handle at
+    /// least one event, so a capacity of zero is treated as one.
+    pub fn set_events_capacity(capacity: usize) {
+        let capacity = capacity.max(1);
+        REACTOR.with(|reactor| *reactor.events.borrow_mut() = Events::with_capacity(capacity))
+    }
+
+    /// Set how the reactor of this thread reports readiness for IO objects that are registered
+    /// without choosing for themselves. Only affects registrations made after this is called.
+    pub fn set_trigger(trigger: Trigger) {
+        REACTOR.with(|reactor| reactor.trigger.set(trigger))
+    }
+}
+
+/// The ways in which the reactor can report that an IO object is ready.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// Report readiness once each time the object becomes ready. Tasks must keep going until their
+    /// IO would block, or they may never be woken again.
+    Edge,
+    /// Report readiness for as long as the object stays ready. The registration is disarmed after
+    /// each report, and re-armed when a task starts waiting on the object again, so that an object
+    /// nobody is waiting on can't keep the reactor spinning.
+    Level,
+}
+
+impl Trigger {
+    /// The options to register an IO object with, to get this kind of readiness reporting.
+    fn poll_opt(self) -> PollOpt {
+        match self {
+            Trigger::Edge => PollOpt::edge(),
+            Trigger::Level => PollOpt::level() | PollOpt::oneshot(),
+        }
+    }
+}
+
+/// The tasks waiting on a single IO object. At most one task can wait for each direction, and once
+/// a waker has been woken it is removed, so that it can't fire again for a later event.
+#[derive(Default)]
+struct Wakers {
+    read: Option<Waker>,
+    write: Option<Waker>,
+}
+
+/// The directions of IO that a task can wait on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Read,
+    Write,
 }
 
 std::thread_local! {
@@ -63,40 +157,86 @@ std::thread_local! {
 /// the reactor, so can not be sent between threads.
 pub struct Handle {
     token: Token,
+    /// How readiness is reported for the IO object registered with this handle.
+    trigger: Cell<Trigger>,
     reactor: PhantomData<*const Reactor>,
 }
 
 impl Handle {
     /// Create a new handle to the reactor on this thread.
     fn new() -> Self {
-        let token = REACTOR.with(|reactor| reactor.tokens.borrow_mut().insert(Vec::new()));
+        let token = REACTOR.with(|reactor| reactor.tokens.borrow_mut().insert(Wakers::default()));
         let token = Token(token);
 
+        let trigger = REACTOR.with(|reactor| Cell::new(reactor.trigger.get()));
         let reactor = PhantomData;
 
-        Handle { token, reactor }
+        Handle {
+            token,
+            trigger,
+            reactor,
+        }
     }
 
-    /// Register a waker with this handle. When the IO object associated with this handle is
-    /// polled, the registered waker will be notified.
-    fn add_waker(&self, waker: Waker) {
+    /// Register a waker with this handle. When the IO object associated with this handle is next
+    /// ready in the given direction, the registered waker will be notified. This replaces any
+    /// waker previously registered for that direction.
+    fn add_waker(&self, direction: Direction, waker: &Waker) {
         let Token(token) = self.token;
         REACTOR.with(|reactor| {
             let wakers = &mut reactor.tokens.borrow_mut()[token];
-
-            if wakers.iter().all(|waker2| !waker2.will_wake(&waker)) {
-                wakers.push(waker);
+            let slot = match direction {
+                Direction::Read => &mut wakers.read,
+                Direction::Write => &mut wakers.write,
+            };
+
+            let stale = match slot {
+                Some(existing) => !existing.will_wake(waker),
+                None => true,
+            };
+            if stale {
+                *slot = Some(waker.clone());
             }
         })
     }
 
-    /// Register an IO capable device with this handle.
-    fn register(&self, io: &impl Evented, interest: Ready, opts: PollOpt) -> Result<(), Error> {
+    /// Register an IO capable device with this handle. Readiness is reported according to the
+    /// given trigger, or according to the reactor's default if there isn't one.
+    fn register(
+        &self,
+        io: &impl Evented,
+        interest: Ready,
+        trigger: Option<Trigger>,
+    ) -> Result<(), Error> {
         REACTOR.with(|reactor| {
-            reactor.inner.register(io, self.token, interest, opts)?;
+            let trigger = trigger.unwrap_or_else(|| reactor.trigger.get());
+            reactor
+                .inner
+                .register(io, self.token, interest, trigger.poll_opt())?;
+            self.trigger.set(trigger);
             Ok(())
         })
     }
+
+    /// Remove an IO capable device from the reactor. This must be done before the device is closed
+    /// or handed to anything else, or the reactor's poll will still hold on to it.
+    fn deregister(&self, io: &impl Evented) -> io::Result<()> {
+        REACTOR.with(|reactor| reactor.inner.deregister(io))
+    }
+
+    /// Re-arm the registration of an IO capable device, after a task has started waiting on it.
+    /// Level triggered registrations are disarmed every time they report readiness, so this must
+    /// be called before every wait. Does nothing for edge triggered registrations.
+    fn rearm(&self, io: &impl Evented, interest: Ready) -> io::Result<()> {
+        match self.trigger.get() {
+            Trigger::Edge => Ok(()),
+            Trigger::Level => REACTOR.with(|reactor| {
+                reactor
+                    .inner
+                    .reregister(io, self.token, interest, Trigger::Level.poll_opt())
+            }),
+        }
+    }
 }
 
 impl Drop for Handle {
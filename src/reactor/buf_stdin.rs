@@ -0,0 +1,139 @@
+use std::{
+    cmp, io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{
+    io::{AsyncBufRead, AsyncRead},
+    ready,
+};
+
+use super::Stdin;
+use crate::error::Error;
+
+/// An asynchronous wrapper around stdin, that buffers its input. Unlike wrapping `Stdin` in a
+/// `BufReader`, the buffered data can be read directly out of the buffer through `AsyncBufRead`.
+pub struct BufStdin {
+    // the unbuffered stdin to fill the buffer from
+    inner: Stdin,
+    // storage for data that has been read from stdin but not yet consumed
+    buffer: Box<[u8]>,
+    // the position of the first byte in the buffer that has not been consumed
+    pos: usize,
+    // the position just past the last byte in the buffer that was read from stdin
+    filled: usize,
+}
+
+impl BufStdin {
+    /// The size of the internal buffer.
+    const CAPACITY: usize = 8 * 1024;
+
+    /// Create a new buffered wrapper around stdin.
+    pub fn new() -> Result<Self, Error> {
+        let inner = Stdin::new()?;
+        let buffer = vec![0; Self::CAPACITY].into_boxed_slice();
+
+        Ok(BufStdin {
+            inner,
+            buffer,
+            pos: 0,
+            filled: 0,
+        })
+    }
+}
+
+impl AsyncRead for BufStdin {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        ctx: &mut Context,
+        buffer: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        // reads that are at least as big as the buffer gain nothing from being copied through it
+        if self.pos == self.filled && buffer.len() >= self.buffer.len() {
+            return Pin::new(&mut self.inner).poll_read(ctx, buffer);
+        }
+
+        let available = ready!(self.as_mut().poll_fill_buf(ctx))?;
+        let read = cmp::min(available.len(), buffer.len());
+        buffer[..read].copy_from_slice(&available[..read]);
+        self.consume(read);
+
+        Poll::Ready(Ok(read))
+    }
+}
+
+impl AsyncBufRead for BufStdin {
+    fn poll_fill_buf(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+
+        if this.pos == this.filled {
+            let read = ready!(Pin::new(&mut this.inner).poll_read(ctx, &mut this.buffer))?;
+            this.pos = 0;
+            this.filled = read;
+        }
+
+        Poll::Ready(Ok(&this.buffer[this.pos..this.filled]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amount: usize) {
+        let this = self.get_mut();
+        this.pos = cmp::min(this.pos + amount, this.filled);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs::File, io::Write};
+
+    use futures::io::{AsyncBufReadExt, AsyncReadExt};
+    use rusty_fork::{fork, rusty_fork_id, ChildWrapper};
+
+    use crate::{
+        executor::Executor,
+        reactor::{
+            stdin::test::{pipe_stdin, wait_for_child},
+            BufStdin,
+        },
+    };
+
+    #[test]
+    fn read_lines() {
+        fn parent(child: &mut ChildWrapper, file: &mut File) {
+            let mut pipe = child.inner_mut().stdin.take().unwrap();
+            write!(pipe, "Hello, world\nGoodbye for now\n").unwrap();
+            drop(pipe);
+
+            wait_for_child(child, file);
+        }
+
+        fn child() {
+            let future = async {
+                let mut input = BufStdin::new().unwrap();
+                let mut buffer = String::new();
+
+                input.read_line(&mut buffer).await.unwrap();
+                assert_eq!("Hello, world\n", buffer);
+                buffer.clear();
+
+                input.read_line(&mut buffer).await.unwrap();
+                assert_eq!("Goodbye for now\n", buffer);
+                buffer.clear();
+
+                assert_eq!(0, input.read_to_string(&mut buffer).await.unwrap());
+            };
+
+            let mut executor = Executor::new();
+            executor.complete(future).unwrap();
+        }
+
+        fork(
+            "reactor::buf_stdin::test::read_lines",
+            rusty_fork_id!(),
+            pipe_stdin,
+            parent,
+            child,
+        )
+        .unwrap();
+    }
+}
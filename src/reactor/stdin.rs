@@ -1,21 +1,24 @@
 use std::{
-    io::{self, ErrorKind, Read},
+    io::{self, ErrorKind, IoSliceMut, Read},
     os::raw::c_int,
     pin::Pin,
     task::{Context, Poll},
 };
 
-use failure::Error;
 use futures::io::AsyncRead;
-use mio::{unix::EventedFd, PollOpt, Ready};
+use mio::{unix::EventedFd, Ready};
 
-use super::Handle;
+use super::{Direction, Handle, Trigger};
+use crate::error::Error;
 
 // Only one handle to stdin can exist at a time. This module defines a singleton mutex.
 mod lock {
-    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::{
+        io::{self, ErrorKind},
+        sync::atomic::{AtomicBool, Ordering},
+    };
 
-    use failure::Error;
+    use crate::error::Error;
 
     // If true, the mutex is locked. If false, the lock is free.
     static STDIN_LOCK: AtomicBool = AtomicBool::new(false);
@@ -28,7 +31,8 @@ mod lock {
         pub fn take() -> Result<Self, Error> {
             if STDIN_LOCK.compare_and_swap(false, true, Ordering::Relaxed) {
                 // the lock was already taken
-                failure::bail!("Stdin is already locked.");
+                let error = io::Error::new(ErrorKind::Other, "Stdin is already locked.");
+                Err(error.into())
             } else {
                 Ok(Guard(()))
             }
@@ -50,24 +54,37 @@ pub struct Stdin {
     old_state: c_int,
     // the stdin object itself for reading from
     inner: io::Stdin,
-    // a handle to the reactor for asynchronous actions
-    handle: Handle,
+    // a handle to the reactor for asynchronous actions, or None if stdin can't be polled (such as
+    // when it is a regular file, or /dev/null)
+    handle: Option<Handle>,
 }
 
 impl Stdin {
-    /// Create a new wrapper around stdin.
+    /// Create a new wrapper around stdin, with readiness reported however the reactor of this
+    /// thread reports it by default.
     pub fn new() -> Result<Self, Error> {
+        Stdin::open(None)
+    }
+
+    /// Create a new wrapper around stdin, with readiness reported according to the given trigger
+    /// rather than the reactor's default.
+    pub fn with_trigger(trigger: Trigger) -> Result<Self, Error> {
+        Stdin::open(Some(trigger))
+    }
+
+    /// Create a new wrapper around stdin, overriding the reactor's trigger if one is given.
+    fn open(trigger: Option<Trigger>) -> Result<Self, Error> {
         let _lock = lock::Guard::take()?;
 
         let old_state = unsafe {
-            let old_state = match libc::fcntl(libc::STDIN_FILENO, libc::F_GETFD) {
+            let old_state = match libc::fcntl(libc::STDIN_FILENO, libc::F_GETFL) {
                 -1 => return Err(io::Error::last_os_error().into()),
                 n => n,
             };
             // set stdin to not block
             match libc::fcntl(
                 libc::STDIN_FILENO,
-                libc::F_SETFD,
+                libc::F_SETFL,
                 old_state | libc::O_NONBLOCK,
             ) {
                 0 => (),
@@ -80,12 +97,22 @@ impl Stdin {
 
         let inner = io::stdin();
 
+        // Files that are always ready, like regular files and /dev/null, can't be registered with
+        // epoll (and some of them can't be registered with kqueue). Reads from them never block, so
+        // they don't need the reactor anyway.
         let handle = Handle::new();
-        handle.register(
-            &EventedFd(&libc::STDIN_FILENO),
-            Ready::readable(),
-            PollOpt::edge(),
-        )?;
+        let handle =
+            match handle.register(&EventedFd(&libc::STDIN_FILENO), Ready::readable(), trigger) {
+                Ok(()) => Some(handle),
+                Err(Error::Io(ref e)) if not_pollable(e) => {
+                    log::debug!("Stdin can't be polled ({}), reading from it directly", e);
+                    None
+                }
+                Err(e) => {
+                    restore_flags(old_state);
+                    return Err(e);
+                }
+            };
 
         Ok(Stdin {
             _lock,
@@ -94,13 +121,68 @@ impl Stdin {
             handle,
         })
     }
+
+    /// Run a read operation against stdin. If the operation would block, the task is registered
+    /// with the reactor to be woken when stdin is readable again.
+    fn poll_io(
+        &mut self,
+        ctx: &mut Context,
+        op: impl FnOnce(&mut io::Stdin) -> io::Result<usize>,
+    ) -> Poll<io::Result<usize>> {
+        match op(&mut self.inner) {
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
+                let handle = match &self.handle {
+                    Some(handle) => handle,
+                    None => {
+                        // there's no way to find out when stdin will be ready, so try again as
+                        // soon as possible
+                        ctx.waker().wake_by_ref();
+                        return Poll::Pending;
+                    }
+                };
+
+                handle.add_waker(Direction::Read, ctx.waker());
+                match handle.rearm(&EventedFd(&libc::STDIN_FILENO), Ready::readable()) {
+                    Ok(()) => Poll::Pending,
+                    Err(e) => Poll::Ready(Err(e)),
+                }
+            }
+            res => Poll::Ready(res),
+        }
+    }
+}
+
+/// Whether an error from registering stdin with the reactor means that stdin is a file that can
+/// never be polled, rather than that something went wrong. epoll refuses files that are always
+/// ready with EPERM, and kqueue refuses devices that it has no read filter for with EINVAL.
+fn not_pollable(error: &io::Error) -> bool {
+    let code = if cfg!(any(target_os = "linux", target_os = "android")) {
+        libc::EPERM
+    } else {
+        libc::EINVAL
+    };
+
+    error.raw_os_error() == Some(code)
 }
 
 impl Drop for Stdin {
     fn drop(&mut self) {
-        unsafe {
-            libc::fcntl(libc::STDIN_FILENO, libc::F_SETFD, self.old_state);
+        // the next Stdin on this thread has to be able to register the fd again
+        if let Some(handle) = &self.handle {
+            if let Err(e) = handle.deregister(&EventedFd(&libc::STDIN_FILENO)) {
+                log::warn!("Failed to remove stdin from the reactor: {}", e);
+            }
         }
+
+        restore_flags(self.old_state);
+    }
+}
+
+/// Put the flags that stdin had before it was made non-blocking back. The file description is
+/// shared with whatever started this process, so it must not be left non-blocking.
+fn restore_flags(old_state: c_int) {
+    unsafe {
+        libc::fcntl(libc::STDIN_FILENO, libc::F_SETFL, old_state);
     }
 }
 
@@ -110,47 +192,90 @@ impl AsyncRead for Stdin {
         ctx: &mut Context,
         buffer: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        match self.as_mut().inner.read(buffer) {
-            Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
-                self.handle.add_waker(ctx.waker().clone());
-                Poll::Pending
-            }
-            res => Poll::Ready(res),
-        }
+        self.poll_io(ctx, |inner| inner.read(buffer))
+    }
+
+    fn poll_read_vectored(
+        mut self: Pin<&mut Self>,
+        ctx: &mut Context,
+        buffers: &mut [IoSliceMut],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_io(ctx, |inner| inner.read_vectored(buffers))
     }
 }
 
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
     use std::{
-        fs::File,
-        io::Write,
-        process::{Command, Stdio},
+        env,
+        fs::{self, File},
+        future::Future,
+        io::{IoSliceMut, Write},
+        pin::Pin,
+        process::{self, Command, Stdio},
+        task::{Context, Poll},
         thread,
         time::{Duration, Instant},
     };
 
-    use futures::{
-        executor,
-        io::{AsyncBufReadExt, AsyncReadExt, BufReader},
-    };
+    use futures::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+    use mio::{unix::EventedFd, Ready};
     use rusty_fork::{fork, rusty_fork_id, ChildWrapper};
 
-    use crate::{executor::Executor, reactor::Stdin};
+    use crate::{
+        executor::Executor,
+        reactor::{Direction, Reactor, Stdin, Trigger},
+    };
 
-    fn pipe_stdin(cmd: &mut Command) {
-        cmd.stdin(Stdio::piped());
+    /// Waits for the reactor to report that stdin is readable, without reading from it.
+    struct Readable<'a> {
+        input: &'a Stdin,
+        waited: bool,
     }
 
-    #[test]
-    fn echo_eventually() {
-        fn parent(child: &mut ChildWrapper, _: &mut File) {
-            write!(child.inner_mut().stdin.take().unwrap(), "Hello, world\n").unwrap();
+    impl<'a> Readable<'a> {
+        fn new(input: &'a Stdin) -> Self {
+            Readable {
+                input,
+                waited: false,
+            }
+        }
+    }
+
+    impl Future for Readable<'_> {
+        type Output = ();
 
-            let status = child.wait().unwrap();
-            assert!(status.success());
+        fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
+            if self.waited {
+                return Poll::Ready(());
+            }
+
+            let handle = self.input.handle.as_ref().unwrap();
+            handle.add_waker(Direction::Read, ctx.waker());
+            handle
+                .rearm(&EventedFd(&libc::STDIN_FILENO), Ready::readable())
+                .unwrap();
+            self.waited = true;
+            Poll::Pending
         }
+    }
 
+    pub(crate) fn pipe_stdin(cmd: &mut Command) {
+        cmd.stdin(Stdio::piped());
+    }
+
+    fn write_then_wait(child: &mut ChildWrapper, file: &mut File) {
+        write!(child.inner_mut().stdin.take().unwrap(), "Hello, world\n").unwrap();
+        wait_for_child(child, file);
+    }
+
+    pub(crate) fn wait_for_child(child: &mut ChildWrapper, _: &mut File) {
+        let status = child.wait().unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn echo_eventually() {
         fn child() {
             let mut input = Stdin::new().unwrap();
             let future = async {
@@ -159,14 +284,43 @@ mod test {
                 assert_eq!("Hello, world\n", buffer);
             };
 
-            executor::block_on(future);
+            let mut executor = Executor::new();
+            executor.complete(future).unwrap();
         }
 
         fork(
             "reactor::stdin::test::echo_eventually",
             rusty_fork_id!(),
             pipe_stdin,
-            parent,
+            write_then_wait,
+            child,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn read_vectored() {
+        fn child() {
+            let mut input = Stdin::new().unwrap();
+            let future = async {
+                let mut first = [0; 5];
+                let mut second = [0; 8];
+                let mut buffers = [IoSliceMut::new(&mut first), IoSliceMut::new(&mut second)];
+
+                assert_eq!(13, input.read_vectored(&mut buffers).await.unwrap());
+                assert_eq!(b"Hello", &first);
+                assert_eq!(b", world\n", &second);
+            };
+
+            let mut executor = Executor::new();
+            executor.complete(future).unwrap();
+        }
+
+        fork(
+            "reactor::stdin::test::read_vectored",
+            rusty_fork_id!(),
+            pipe_stdin,
+            write_then_wait,
             child,
         )
         .unwrap();
@@ -174,7 +328,7 @@ mod test {
 
     #[test]
     fn run_in_executor() {
-        fn parent(child: &mut ChildWrapper, _: &mut File) {
+        fn parent(child: &mut ChildWrapper, file: &mut File) {
             let mut pipe = child.inner_mut().stdin.take().unwrap();
 
             write!(pipe, "Hello, world\n").unwrap();
@@ -185,8 +339,7 @@ mod test {
 
             drop(pipe);
 
-            let status = child.wait().unwrap();
-            assert!(status.success());
+            wait_for_child(child, file);
         }
 
         fn child() {
@@ -226,4 +379,174 @@ mod test {
         )
         .unwrap();
     }
+
+    #[test]
+    fn open_twice() {
+        fn child() {
+            let first = Stdin::new().unwrap();
+            assert!(first.handle.is_some());
+            drop(first);
+
+            // the pipe is still pollable, so this has to register with the reactor again rather
+            // than fall back to reading from it directly
+            let mut input = Stdin::new().unwrap();
+            assert!(input.handle.is_some());
+            let future = async {
+                let mut buffer = String::new();
+                input.read_to_string(&mut buffer).await.unwrap();
+                assert_eq!("Hello, world\n", buffer);
+            };
+
+            let mut executor = Executor::new();
+            executor.complete(future).unwrap();
+        }
+
+        fork(
+            "reactor::stdin::test::open_twice",
+            rusty_fork_id!(),
+            pipe_stdin,
+            write_then_wait,
+            child,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn level_triggered() {
+        const LEN: usize = 16 * 1024;
+
+        fn message() -> Vec<u8> {
+            (0..LEN).map(|i| i as u8).collect()
+        }
+
+        fn parent(child: &mut ChildWrapper, file: &mut File) {
+            let mut pipe = child.inner_mut().stdin.take().unwrap();
+            pipe.write_all(&message()).unwrap();
+
+            // keep the pipe open, so that hanging up can't make stdin look ready
+            wait_for_child(child, file);
+            drop(pipe);
+        }
+
+        fn child() {
+            Reactor::set_trigger(Trigger::Level);
+            let mut input = Stdin::new().unwrap();
+            assert_eq!(Trigger::Level, input.handle.as_ref().unwrap().trigger.get());
+
+            let future = async {
+                Readable::new(&input).await;
+                // let the whole message land in the pipe
+                thread::sleep(Duration::from_millis(200));
+
+                // std's stdin only skips its 8 KiB buffer for reads at least that big, so this
+                // leaves the rest of the message in the pipe rather than in the buffer
+                let mut output = vec![0; 2 * LEN];
+                let first = input.read(&mut output[..9000]).await.unwrap();
+                assert_eq!(9000, first);
+
+                // stdin is still readable without any new data arriving, which a level triggered
+                // registration reports straight away, and an edge triggered one never would
+                Readable::new(&input).await;
+                let rest = input.read(&mut output[first..]).await.unwrap();
+                assert_eq!(LEN, first + rest);
+                assert_eq!(message(), &output[..LEN]);
+            };
+
+            let mut executor = Executor::new();
+            executor.complete(future).unwrap();
+        }
+
+        fork(
+            "reactor::stdin::test::level_triggered",
+            rusty_fork_id!(),
+            pipe_stdin,
+            parent,
+            child,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn trigger_override() {
+        fn child() {
+            Reactor::set_trigger(Trigger::Level);
+            let mut input = Stdin::with_trigger(Trigger::Edge).unwrap();
+            assert_eq!(Trigger::Edge, input.handle.as_ref().unwrap().trigger.get());
+
+            let future = async {
+                let mut buffer = String::new();
+                input.read_to_string(&mut buffer).await.unwrap();
+                assert_eq!("Hello, world\n", buffer);
+            };
+
+            let mut executor = Executor::new();
+            executor.complete(future).unwrap();
+        }
+
+        fork(
+            "reactor::stdin::test::trigger_override",
+            rusty_fork_id!(),
+            pipe_stdin,
+            write_then_wait,
+            child,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn regular_file() {
+        let path = env::temp_dir().join(format!("nt-stdin-test-{}", process::id()));
+
+        fn child() {
+            let mut input = Stdin::new().unwrap();
+            let future = async {
+                let mut buffer = String::new();
+                input.read_to_string(&mut buffer).await.unwrap();
+                assert_eq!("Hello, world\n", buffer);
+            };
+
+            let mut executor = Executor::new();
+            executor.complete(future).unwrap();
+        }
+
+        fork(
+            "reactor::stdin::test::regular_file",
+            rusty_fork_id!(),
+            |cmd| {
+                fs::write(&path, "Hello, world\n").unwrap();
+                cmd.stdin(File::open(&path).unwrap());
+            },
+            |child, file| {
+                wait_for_child(child, file);
+                fs::remove_file(&path).unwrap();
+            },
+            child,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn dev_null() {
+        fn child() {
+            let mut input = Stdin::new().unwrap();
+            let future = async {
+                let mut buffer = Vec::new();
+                assert_eq!(0, input.read_to_end(&mut buffer).await.unwrap());
+            };
+
+            let mut executor = Executor::new();
+            executor.complete(future).unwrap();
+        }
+
+        fork(
+            "reactor::stdin::test::dev_null",
+            rusty_fork_id!(),
+            |cmd| {
+                cmd.stdin(Stdio::null());
+            },
+            wait_for_child,
+            child,
+        )
+        .unwrap();
+    }
 }
@@ -0,0 +1,7 @@
+//! Synchronisation primitives for the parts of the runtime that can be reached from other threads.
+//! Building with `--cfg loom` swaps them out for loom's model checked versions.
+
+#[cfg(loom)]
+pub(crate) use loom::sync::{Arc, Mutex};
+#[cfg(not(loom))]
+pub(crate) use std::sync::{Arc, Mutex};
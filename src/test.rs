@@ -0,0 +1,249 @@
+//! Utilities for testing code that runs on this runtime: assertions about polling, a waker that
+//! counts how often it is woken, and a scriptable IO object.
+
+use std::{
+    collections::VecDeque,
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Waker},
+    thread,
+};
+
+use futures::{
+    io::{AsyncRead, AsyncWrite},
+    task::{self, ArcWake},
+};
+
+/// Assert that a poll returned `Poll::Pending`.
+#[macro_export]
+macro_rules! assert_pending {
+    ($poll:expr) => {
+        match $poll {
+            ::std::task::Poll::Pending => (),
+            ::std::task::Poll::Ready(value) => {
+                panic!("Expected Poll::Pending, got Poll::Ready({:?})", value)
+            }
+        }
+    };
+}
+
+/// Assert that a poll returned `Poll::Ready`, and evaluate to the value inside it.
+#[macro_export]
+macro_rules! assert_ready {
+    ($poll:expr) => {
+        match $poll {
+            ::std::task::Poll::Ready(value) => value,
+            ::std::task::Poll::Pending => panic!("Expected Poll::Ready, got Poll::Pending"),
+        }
+    };
+}
+
+/// A waker that keeps count of how many times it has been woken. Used to check that futures
+/// arrange to be woken before returning `Poll::Pending`, and aren't woken needlessly.
+#[derive(Clone, Default)]
+pub struct WakeCounter {
+    count: Arc<Count>,
+}
+
+#[derive(Default)]
+struct Count(AtomicUsize);
+
+impl ArcWake for Count {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl WakeCounter {
+    /// Create a new counter, that has not been woken yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get a waker that increments this counter when woken.
+    pub fn waker(&self) -> Waker {
+        task::waker(self.count.clone())
+    }
+
+    /// The number of times that any of this counter's wakers have been woken.
+    pub fn count(&self) -> usize {
+        self.count.0.load(Ordering::SeqCst)
+    }
+}
+
+/// A step in the script of a mock stream.
+#[derive(Debug)]
+enum Action {
+    /// Return this data from reads.
+    Read(Vec<u8>),
+    /// Expect exactly this data to be written.
+    Write(Vec<u8>),
+    /// Return `Poll::Pending` once, waking the task straight away.
+    Wait,
+}
+
+/// Builds up the script for a mock stream.
+#[derive(Debug, Default)]
+pub struct Builder {
+    actions: VecDeque<Action>,
+}
+
+impl Builder {
+    /// Have the stream return this data from reads.
+    pub fn read(&mut self, data: &[u8]) -> &mut Self {
+        self.actions.push_back(Action::Read(data.to_vec()));
+        self
+    }
+
+    /// Have the stream expect exactly this data to be written to it.
+    pub fn write(&mut self, data: &[u8]) -> &mut Self {
+        self.actions.push_back(Action::Write(data.to_vec()));
+        self
+    }
+
+    /// Have the next read or write return `Poll::Pending`, waking the task straight away.
+    pub fn wait(&mut self) -> &mut Self {
+        self.actions.push_back(Action::Wait);
+        self
+    }
+
+    /// Create a stream that follows the script built up so far.
+    pub fn build(&mut self) -> MockStream {
+        MockStream {
+            actions: self.actions.drain(..).collect(),
+            read_waker: None,
+            write_waker: None,
+        }
+    }
+}
+
+/// An IO object that follows a script of reads and writes. Reads return the scripted data, and
+/// writes are checked against the scripted data, panicking if they don't match. A read that comes
+/// while a write is expected (or the other way around) returns `Poll::Pending` until the script
+/// moves on. Once the script is finished, reads return end of file. Dropping the stream before its
+/// script is finished panics.
+#[derive(Debug)]
+pub struct MockStream {
+    actions: VecDeque<Action>,
+    read_waker: Option<Waker>,
+    write_waker: Option<Waker>,
+}
+
+impl MockStream {
+    /// Start writing the script for a new stream.
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
+    /// Move on to the next action in the script, waking any task that was waiting for it.
+    fn advance(&mut self) {
+        self.actions.pop_front();
+
+        let (wake_reader, wake_writer) = match self.actions.front() {
+            Some(Action::Read(_)) => (true, false),
+            Some(Action::Write(_)) => (false, true),
+            // either side can take the next step, or see that the script is over
+            Some(Action::Wait) | None => (true, true),
+        };
+
+        if wake_reader {
+            if let Some(waker) = self.read_waker.take() {
+                waker.wake();
+            }
+        }
+        if wake_writer {
+            if let Some(waker) = self.write_waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl AsyncRead for MockStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        ctx: &mut Context,
+        buffer: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        match this.actions.front_mut() {
+            None => Poll::Ready(Ok(0)),
+            Some(Action::Read(data)) => {
+                let read = data.len().min(buffer.len());
+                buffer[..read].copy_from_slice(&data[..read]);
+                data.drain(..read);
+                if data.is_empty() {
+                    this.advance();
+                }
+                Poll::Ready(Ok(read))
+            }
+            Some(Action::Write(_)) => {
+                this.read_waker = Some(ctx.waker().clone());
+                Poll::Pending
+            }
+            Some(Action::Wait) => {
+                this.advance();
+                ctx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl AsyncWrite for MockStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        ctx: &mut Context,
+        buffer: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        match this.actions.front_mut() {
+            None => panic!("Unexpected write of {:?}", buffer),
+            Some(Action::Write(expected)) => {
+                let written = expected.len().min(buffer.len());
+                assert_eq!(&expected[..written], &buffer[..written], "Unexpected write");
+                expected.drain(..written);
+                if expected.is_empty() {
+                    this.advance();
+                }
+                Poll::Ready(Ok(written))
+            }
+            Some(Action::Read(_)) => {
+                this.write_waker = Some(ctx.waker().clone());
+                Poll::Pending
+            }
+            Some(Action::Wait) => {
+                this.advance();
+                ctx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for MockStream {
+    fn drop(&mut self) {
+        // don't turn an existing panic into an abort
+        if !thread::panicking() {
+            assert!(
+                self.actions.is_empty(),
+                "Mock stream dropped with unfinished script: {:?}",
+                self.actions
+            );
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Checks that the executor, and the IO types and codecs built on the runtime, keep to the rules
+//! of `std::task`: a future only returns `Poll::Pending` once it has arranged to be woken, and a
+//! woken task is polled again.
+
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+
+use futures::{
+    future,
+    io::{AsyncReadExt, AsyncWriteExt},
+    stream::Stream,
+};
+use net_tiger::{
+    assert_pending, assert_ready,
+    codec::{LengthDelimited, Lines},
+    executor::Executor,
+    io::copy_bidirectional,
+    test::{MockStream, WakeCounter},
+};
+
+/// Counts how many spawned tasks have finished, so that the main future can wait for them.
+#[derive(Clone, Default)]
+struct Finished(Rc<RefCell<Tally>>);
+
+#[derive(Default)]
+struct Tally {
+    count: usize,
+    waiting: Option<Waker>,
+}
+
+impl Finished {
+    /// Record that a task has finished, waking the main future if it is waiting.
+    fn finish(&self) {
+        let waiting = {
+            let mut tally = self.0.borrow_mut();
+            tally.count += 1;
+            tally.waiting.take()
+        };
+
+        if let Some(waker) = waiting {
+            waker.wake();
+        }
+    }
+
+    /// Wait until at least `count` tasks have finished.
+    fn wait_for(&self, count: usize) -> impl Future<Output = ()> + '_ {
+        future::poll_fn(move |ctx| {
+            let mut tally = self.0.borrow_mut();
+            if tally.count >= count {
+                Poll::Ready(())
+            } else {
+                tally.waiting = Some(ctx.waker().clone());
+                Poll::Pending
+            }
+        })
+    }
+}
+
+#[test]
+fn executor_polls_woken_main_future() {
+    let mut stream = MockStream::builder()
+        .wait()
+        .read(b"Hello")
+        .wait()
+        .write(b"Goodbye")
+        .build();
+
+    let future = async {
+        let mut buffer = [0; 5];
+        stream.read_exact(&mut buffer).await.unwrap();
+        stream.write_all(b"Goodbye").await.unwrap();
+        buffer
+    };
+
+    let mut executor = Executor::new();
+    assert_eq!(b"Hello", &executor.complete(future).unwrap());
+}
+
+#[test]
+fn executor_polls_woken_spawned_tasks() {
+    let finished = Finished::default();
+    let mut executor = Executor::new();
+
+    for _ in 0..8usize {
+        let finished = finished.clone();
+        executor.spawn(async move {
+            let mut stream = MockStream::builder().wait().read(b"Hi").wait().build();
+            let mut buffer = Vec::new();
+            stream.read_to_end(&mut buffer).await.unwrap();
+            assert_eq!(b"Hi", &buffer[..]);
+            finished.finish();
+        });
+    }
+
+    executor.complete(finished.wait_for(8)).unwrap();
+}
+
+/// A future that wakes itself twice before returning `Poll::Pending`, and completes the next time
+/// it is polled. The second wake is still queued when it completes.
+struct WakeTwice(bool);
+
+impl Future for WakeTwice {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+
+        ctx.waker().wake_by_ref();
+        ctx.waker().wake_by_ref();
+        self.0 = true;
+        Poll::Pending
+    }
+}
+
+#[test]
+fn executor_allows_waking_more_than_once() {
+    let finished = Finished::default();
+    let mut executor = Executor::new();
+
+    let task_finished = finished.clone();
+    executor.spawn(async move {
+        WakeTwice(false).await;
+        task_finished.finish();
+    });
+
+    executor.complete(finished.wait_for(1)).unwrap();
+}
+
+#[test]
+fn copy_bidirectional_follows_the_conversation() {
+    let mut client = MockStream::builder().read(b"ping").write(b"pong").build();
+    let mut server = MockStream::builder().write(b"ping").read(b"pong").build();
+
+    let future = copy_bidirectional(&mut client, &mut server);
+    let mut executor = Executor::new();
+    assert_eq!((4, 4), executor.complete(future).unwrap().unwrap());
+}
+
+#[test]
+fn length_delimited_wakes_before_pending() {
+    let counter = WakeCounter::new();
+    let waker = counter.waker();
+    let mut ctx = Context::from_waker(&waker);
+
+    let stream = MockStream::builder()
+        .read(&[0, 0])
+        .wait()
+        .read(&[0, 2, b'h', b'i'])
+        .build();
+    let mut frames = LengthDelimited::new(stream);
+
+    assert_pending!(Pin::new(&mut frames).poll_next(&mut ctx));
+    assert_eq!(1, counter.count());
+
+    let frame = assert_ready!(Pin::new(&mut frames).poll_next(&mut ctx));
+    assert_eq!(b"hi", &frame.unwrap().unwrap()[..]);
+    assert!(assert_ready!(Pin::new(&mut frames).poll_next(&mut ctx)).is_none());
+    assert_eq!(1, counter.count());
+}
+
+#[test]
+fn lines_wakes_before_pending() {
+    let counter = WakeCounter::new();
+    let waker = counter.waker();
+    let mut ctx = Context::from_waker(&waker);
+
+    let stream = MockStream::builder()
+        .read(b"Hello, ")
+        .wait()
+        .read(b"world\n")
+        .build();
+    let mut lines = Lines::new(stream);
+
+    assert_pending!(Pin::new(&mut lines).poll_next(&mut ctx));
+    assert_eq!(1, counter.count());
+
+    let line = assert_ready!(Pin::new(&mut lines).poll_next(&mut ctx));
+    assert_eq!(b"Hello, world\n", &line.unwrap().unwrap()[..]);
+    assert!(assert_ready!(Pin::new(&mut lines).poll_next(&mut ctx)).is_none());
+    assert_eq!(1, counter.count());
+}